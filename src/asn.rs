@@ -0,0 +1,465 @@
+use async_trait::async_trait;
+use clap::ValueEnum;
+use reqwest::{Client, RequestBuilder};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{retry_delay, DnsResult};
+
+/// ASN and, when requested, geographic details for a single IP address.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsnInfo {
+    pub asn: String,
+    pub as_name: String,
+    pub country: Option<String>,
+    pub country_code: Option<String>,
+    pub city: Option<String>,
+    pub org: Option<String>,
+}
+
+/// Source of ASN details for IP addresses.
+#[async_trait]
+pub trait AsnProvider: Send + Sync {
+    async fn lookup(&self, ip: IpAddr) -> Option<AsnInfo>;
+
+    /// Look up many IPs at once. IPs without an answer are absent from the
+    /// returned map; providers with a bulk API override this.
+    async fn lookup_batch(&self, ips: &[IpAddr]) -> HashMap<IpAddr, AsnInfo> {
+        let mut found = HashMap::new();
+        for &ip in ips {
+            if let Some(info) = self.lookup(ip).await {
+                found.insert(ip, info);
+            }
+        }
+        found
+    }
+}
+
+#[derive(Deserialize)]
+struct IpApiResponse {
+    query: String,
+    status: String,
+    #[serde(rename = "as", default)]
+    asn: String,
+    #[serde(rename = "asname", default)]
+    as_name: String,
+    country: Option<String>,
+    #[serde(rename = "countryCode")]
+    country_code: Option<String>,
+    city: Option<String>,
+    org: Option<String>,
+}
+
+impl IpApiResponse {
+    fn into_info(self) -> Option<(IpAddr, AsnInfo)> {
+        if self.status != "success" {
+            return None;
+        }
+        let ip = self.query.parse::<IpAddr>().ok()?;
+        Some((
+            ip,
+            AsnInfo {
+                asn: self.asn,
+                as_name: self.as_name,
+                country: self.country,
+                country_code: self.country_code,
+                city: self.city,
+                org: self.org,
+            },
+        ))
+    }
+}
+
+/// Optional ip-api.com fields, named as in its API. Fields that aren't
+/// requested are left empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GeoField {
+    #[value(name = "country")]
+    Country,
+    #[value(name = "countryCode")]
+    CountryCode,
+    #[value(name = "city")]
+    City,
+    #[value(name = "org")]
+    Org,
+}
+
+impl GeoField {
+    fn as_str(&self) -> &'static str {
+        match self {
+            GeoField::Country => "country",
+            GeoField::CountryCode => "countryCode",
+            GeoField::City => "city",
+            GeoField::Org => "org",
+        }
+    }
+}
+
+/// Looks up ASNs over HTTP with ip-api.com. Only the ASN and the given
+/// geographic fields are requested, to keep responses small. Failed or
+/// timed out requests are retried `retries` times with exponential backoff.
+/// Every request goes through `client`, so connections are reused across
+/// lookups.
+pub struct IpApiProvider {
+    pub client: Client,
+    pub geo_fields: Vec<GeoField>,
+    pub retries: u32,
+    pub retry_backoff: Duration,
+}
+
+impl IpApiProvider {
+    /// Send the request built by `request` until it succeeds or the retries
+    /// run out, returning the last error.
+    async fn fetch<T: DeserializeOwned>(&self, request: impl Fn(&Client) -> RequestBuilder) -> reqwest::Result<T> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let response = request(&self.client).send().await.and_then(reqwest::Response::error_for_status);
+            let outcome = match response {
+                Ok(response) => response.json::<T>().await,
+                Err(e) => Err(e),
+            };
+            match outcome {
+                Err(e) if attempt <= self.retries => {
+                    tracing::debug!("ip-api.com request failed ({}), retrying", e);
+                    tokio::time::sleep(retry_delay(self.retry_backoff, attempt)).await;
+                }
+                outcome => return outcome,
+            }
+        }
+    }
+
+    fn fields(&self) -> String {
+        let mut fields = vec!["query", "status", "as", "asname"];
+        fields.extend(self.geo_fields.iter().map(GeoField::as_str));
+        fields.join(",")
+    }
+}
+
+#[async_trait]
+impl AsnProvider for IpApiProvider {
+    async fn lookup(&self, ip: IpAddr) -> Option<AsnInfo> {
+        let url = format!("http://ip-api.com/json/{}?fields={}", ip, self.fields());
+        match self.fetch::<IpApiResponse>(|client| client.get(&url)).await {
+            Ok(data) => data.into_info().map(|(_, info)| info),
+            Err(e) => {
+                tracing::debug!("ASN lookup of {} failed: {}", ip, e);
+                None
+            }
+        }
+    }
+
+    /// Uses ip-api.com's batch endpoint, at most 100 IPs per request.
+    async fn lookup_batch(&self, ips: &[IpAddr]) -> HashMap<IpAddr, AsnInfo> {
+        let url = format!("http://ip-api.com/batch?fields={}", self.fields());
+        let mut found = HashMap::new();
+
+        for chunk in ips.chunks(100) {
+            let entries = match self.fetch::<Vec<IpApiResponse>>(|client| client.post(&url).json(chunk)).await {
+                Ok(entries) => entries,
+                Err(e) => {
+                    tracing::debug!("ASN batch lookup of {} IPs failed: {}", chunk.len(), e);
+                    continue;
+                }
+            };
+
+            found.extend(entries.into_iter().filter_map(IpApiResponse::into_info));
+        }
+
+        found
+    }
+}
+
+/// Looks up ASNs in a local MaxMind GeoLite2-ASN database.
+pub struct MaxMindProvider {
+    reader: maxminddb::Reader<Vec<u8>>,
+}
+
+impl MaxMindProvider {
+    pub fn open(path: &str) -> Result<Self, maxminddb::MaxMindDbError> {
+        Ok(MaxMindProvider {
+            reader: maxminddb::Reader::open_readfile(path)?,
+        })
+    }
+}
+
+#[async_trait]
+impl AsnProvider for MaxMindProvider {
+    /// The ASN is formatted like ip-api.com's `as` field ("AS15169 Google
+    /// LLC"); the organization doubles as the AS name since the database
+    /// has no separate handle.
+    async fn lookup(&self, ip: IpAddr) -> Option<AsnInfo> {
+        let record = self
+            .reader
+            .lookup(ip)
+            .ok()?
+            .decode::<maxminddb::geoip2::Asn>()
+            .ok()??;
+        let number = record.autonomous_system_number?;
+        let organization = record.autonomous_system_organization.unwrap_or_default();
+
+        Some(AsnInfo {
+            asn: format!("AS{} {}", number, organization),
+            as_name: organization.to_string(),
+            country: None,
+            country_code: None,
+            city: None,
+            org: None,
+        })
+    }
+}
+
+type AsnEntries = HashMap<IpAddr, Option<AsnInfo>>;
+
+/// Per-run cache of ASN lookups keyed by IP, so each unique address is
+/// looked up only once. Providers are tried in order, each one only for the
+/// addresses the previous ones could not answer.
+#[derive(Clone)]
+pub struct AsnCache {
+    entries: Arc<Mutex<AsnEntries>>,
+    providers: Arc<Vec<Box<dyn AsnProvider>>>,
+}
+
+impl AsnCache {
+    pub fn new(providers: Vec<Box<dyn AsnProvider>>) -> Self {
+        AsnCache {
+            entries: Arc::default(),
+            providers: Arc::new(providers),
+        }
+    }
+
+    /// Fill in ASN details for every result with an IP address, and the ASN
+    /// of each `--follow-mx` exchanger address, batching all addresses not
+    /// yet cached into as few provider requests as possible.
+    pub async fn enrich(&self, results: &mut [DnsResult]) {
+        let mut missing = Vec::new();
+        {
+            let entries = self.entries.lock().expect("ASN cache lock poisoned");
+            for result in results.iter() {
+                let mx_ips = result.mx_targets.iter().flatten().filter_map(|target| target.ip.as_deref());
+                for ip in result.ip_address.as_deref().into_iter().chain(mx_ips) {
+                    let Ok(ip) = ip.parse::<IpAddr>() else { continue };
+                    if !entries.contains_key(&ip) && !missing.contains(&ip) {
+                        missing.push(ip);
+                    }
+                }
+            }
+        }
+
+        let mut found = HashMap::new();
+        for provider in self.providers.iter() {
+            if missing.len() == found.len() {
+                break;
+            }
+            let pending = missing
+                .iter()
+                .filter(|ip| !found.contains_key(*ip))
+                .copied()
+                .collect::<Vec<_>>();
+            found.extend(provider.lookup_batch(&pending).await);
+        }
+
+        let mut entries = self.entries.lock().expect("ASN cache lock poisoned");
+        for ip in missing {
+            entries.insert(ip, found.remove(&ip));
+        }
+
+        for result in results.iter_mut() {
+            for target in result.mx_targets.iter_mut().flatten() {
+                let Some(Ok(ip)) = target.ip.as_deref().map(str::parse::<IpAddr>) else {
+                    continue;
+                };
+                if let Some(Some(info)) = entries.get(&ip) {
+                    target.asn = Some(info.asn.clone());
+                }
+            }
+            let Some(Ok(ip)) = result.ip_address.as_deref().map(str::parse::<IpAddr>) else {
+                continue;
+            };
+            if let Some(Some(info)) = entries.get(&ip) {
+                result.asn = Some(info.asn.clone());
+                result.as_name = Some(info.as_name.clone());
+                result.country = info.country.clone();
+                result.country_code = info.country_code.clone();
+                result.city = info.city.clone();
+                result.org = info.org.clone();
+            }
+        }
+    }
+}
+
+/// Extract the AS number from an ASN such as "AS15169 Google LLC",
+/// "AS15169" or "15169".
+pub fn asn_number(asn: &str) -> Option<u32> {
+    let asn = asn.trim();
+    let asn = asn
+        .strip_prefix("AS")
+        .or_else(|| asn.strip_prefix("as"))
+        .unwrap_or(asn);
+    let digits = asn.split_whitespace().next()?;
+    digits.parse().ok()
+}
+
+/// Allow and deny lists of AS numbers applied after enrichment. An empty
+/// allow list permits every ASN; hosts without ASN details never pass a
+/// non-empty allow list but are never denied.
+#[derive(Debug, Clone, Default)]
+pub struct AsnFilter {
+    pub allow: Vec<u32>,
+    pub deny: Vec<u32>,
+}
+
+impl AsnFilter {
+    pub fn is_active(&self) -> bool {
+        !self.allow.is_empty() || !self.deny.is_empty()
+    }
+
+    pub fn permits(&self, result: &DnsResult) -> bool {
+        let number = result.asn.as_deref().and_then(asn_number);
+        if !self.allow.is_empty() && !number.is_some_and(|n| self.allow.contains(&n)) {
+            return false;
+        }
+        !number.is_some_and(|n| self.deny.contains(&n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests::dns_result, MxTarget};
+
+    fn with_asn(asn: Option<&str>) -> DnsResult {
+        let mut result = dns_result("example.com");
+        result.asn = asn.map(str::to_string);
+        result
+    }
+
+    #[test]
+    fn asn_numbers_are_parsed() {
+        assert_eq!(asn_number("AS15169 Google LLC"), Some(15169));
+        assert_eq!(asn_number("as15169"), Some(15169));
+        assert_eq!(asn_number("15169"), Some(15169));
+        assert_eq!(asn_number(" AS13335 "), Some(13335));
+        assert_eq!(asn_number(""), None);
+        assert_eq!(asn_number("Google"), None);
+    }
+
+    #[test]
+    fn allow_list_rejects_other_and_unknown_asns() {
+        let filter = AsnFilter { allow: vec![15169], deny: Vec::new() };
+        assert!(filter.is_active());
+        assert!(filter.permits(&with_asn(Some("AS15169 Google LLC"))));
+        assert!(!filter.permits(&with_asn(Some("AS13335 Cloudflare"))));
+        assert!(!filter.permits(&with_asn(None)));
+        assert!(!filter.permits(&with_asn(Some("unparseable"))));
+    }
+
+    #[test]
+    fn deny_list_takes_precedence() {
+        let filter = AsnFilter { allow: vec![15169, 13335], deny: vec![13335] };
+        assert!(filter.permits(&with_asn(Some("AS15169 Google LLC"))));
+        assert!(!filter.permits(&with_asn(Some("AS13335 Cloudflare"))));
+
+        let filter = AsnFilter { allow: Vec::new(), deny: vec![13335] };
+        assert!(filter.permits(&with_asn(Some("AS15169 Google LLC"))));
+        assert!(!filter.permits(&with_asn(Some("13335"))));
+        assert!(filter.permits(&with_asn(None)));
+    }
+
+    #[test]
+    fn empty_filter_permits_everything() {
+        let filter = AsnFilter::default();
+        assert!(!filter.is_active());
+        assert!(filter.permits(&with_asn(None)));
+        assert!(filter.permits(&with_asn(Some("AS13335 Cloudflare"))));
+    }
+    /// Answers from a fixed table and records every IP it is asked about.
+    struct StubProvider {
+        known: HashMap<IpAddr, &'static str>,
+        asked: Arc<Mutex<Vec<IpAddr>>>,
+    }
+
+    impl StubProvider {
+        fn new(known: &[(&str, &'static str)]) -> (Self, Arc<Mutex<Vec<IpAddr>>>) {
+            let asked = Arc::default();
+            let provider = StubProvider {
+                known: known.iter().map(|&(ip, asn)| (ip.parse().unwrap(), asn)).collect(),
+                asked: Arc::clone(&asked),
+            };
+            (provider, asked)
+        }
+    }
+
+    #[async_trait]
+    impl AsnProvider for StubProvider {
+        async fn lookup(&self, ip: IpAddr) -> Option<AsnInfo> {
+            self.asked.lock().unwrap().push(ip);
+            self.known.get(&ip).map(|&asn| AsnInfo {
+                asn: asn.to_string(),
+                as_name: asn.to_string(),
+                country: None,
+                country_code: None,
+                city: None,
+                org: None,
+            })
+        }
+    }
+
+    fn with_ip(hostname: &str, ip: &str) -> DnsResult {
+        let mut result = dns_result(hostname);
+        result.ip_address = Some(ip.to_string());
+        result
+    }
+
+    fn asked(asked: &Arc<Mutex<Vec<IpAddr>>>) -> Vec<String> {
+        asked.lock().unwrap().iter().map(IpAddr::to_string).collect()
+    }
+
+    #[tokio::test]
+    async fn enrich_falls_back_and_queries_each_ip_once() {
+        let (first, first_asked) = StubProvider::new(&[("192.0.2.1", "AS1 First")]);
+        let (second, second_asked) =
+            StubProvider::new(&[("192.0.2.1", "AS9 Unused"), ("192.0.2.2", "AS2 Second"), ("192.0.2.25", "AS3 Mail")]);
+        let cache = AsnCache::new(vec![Box::new(first), Box::new(second)]);
+
+        let mut mail = with_ip("mail.example.com", "192.0.2.1");
+        mail.mx_targets = Some(vec![MxTarget {
+            preference: 10,
+            exchange: "mx.example.com".into(),
+            ip: Some("192.0.2.25".into()),
+            asn: None,
+        }]);
+        let mut results = vec![
+            with_ip("a.example.com", "192.0.2.1"),
+            with_ip("b.example.com", "192.0.2.1"),
+            with_ip("c.example.com", "192.0.2.2"),
+            with_ip("d.example.com", "192.0.2.99"),
+            dns_result("no-ip.example.com"),
+            mail,
+        ];
+        results[4].ip_address = None;
+        cache.enrich(&mut results).await;
+
+        // The second provider is only asked what the first couldn't answer
+        assert_eq!(asked(&first_asked), ["192.0.2.1", "192.0.2.2", "192.0.2.99", "192.0.2.25"]);
+        assert_eq!(asked(&second_asked), ["192.0.2.2", "192.0.2.99", "192.0.2.25"]);
+
+        let asns: Vec<_> = results.iter().map(|result| result.asn.as_deref()).collect();
+        assert_eq!(asns, [Some("AS1 First"), Some("AS1 First"), Some("AS2 Second"), None, None, Some("AS1 First")]);
+        let mx = &results[5].mx_targets.as_ref().unwrap()[0];
+        assert_eq!(mx.asn.as_deref(), Some("AS3 Mail"));
+
+        // Answers and misses are cached, so nothing is asked again
+        let mut again = vec![with_ip("e.example.com", "192.0.2.2"), with_ip("f.example.com", "192.0.2.99")];
+        cache.enrich(&mut again).await;
+        assert_eq!(first_asked.lock().unwrap().len(), 4);
+        assert_eq!(second_asked.lock().unwrap().len(), 3);
+        assert_eq!(again[0].asn.as_deref(), Some("AS2 Second"));
+        assert_eq!(again[1].asn, None);
+    }
+}
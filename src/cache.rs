@@ -0,0 +1,120 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+use tokio::time::Instant;
+
+use crate::{FoundRecords, QueryType};
+
+type Entries = HashMap<(String, QueryType), (FoundRecords, Instant)>;
+
+/// Per-run cache of successful lookups keyed by name and record type, so a
+/// name repeated in the input is only queried again once its TTL runs out.
+/// TTLs below `min_ttl` are raised to it. Expired entries are dropped when
+/// they are next looked up.
+#[derive(Debug)]
+pub struct DnsCache {
+    entries: Mutex<Entries>,
+    min_ttl: Duration,
+}
+
+impl DnsCache {
+    pub fn new(min_ttl: Duration) -> Self {
+        DnsCache {
+            entries: Mutex::default(),
+            min_ttl,
+        }
+    }
+
+    /// The cached answer for `hostname`, with its TTL counted down to what
+    /// is left of it.
+    pub fn get(&self, hostname: &str, record_type: QueryType) -> Option<FoundRecords> {
+        let mut entries = self.entries.lock().expect("DNS cache lock poisoned");
+        let key = (hostname.to_string(), record_type);
+        let (found, expires) = entries.get(&key)?;
+        let Some(remaining) = expires.checked_duration_since(Instant::now()) else {
+            entries.remove(&key);
+            return None;
+        };
+        Some(FoundRecords {
+            records: found.records.clone(),
+            ttl: Some(remaining.as_secs() as u32),
+        })
+    }
+
+    pub fn insert(&self, hostname: &str, record_type: QueryType, found: &FoundRecords) {
+        let ttl = Duration::from_secs(found.ttl.unwrap_or(0).into()).max(self.min_ttl);
+        if ttl.is_zero() {
+            return;
+        }
+        let entry = FoundRecords {
+            records: found.records.clone(),
+            ttl: found.ttl,
+        };
+        self.entries
+            .lock()
+            .expect("DNS cache lock poisoned")
+            .insert((hostname.to_string(), record_type), (entry, Instant::now() + ttl));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::advance;
+
+    fn found(ttl: Option<u32>) -> FoundRecords {
+        FoundRecords {
+            records: vec!["192.0.2.1".to_string()],
+            ttl,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn entries_count_down_and_expire() {
+        let cache = DnsCache::new(Duration::ZERO);
+        cache.insert("example.com", QueryType::A, &found(Some(60)));
+        assert_eq!(cache.get("example.com", QueryType::A).unwrap().ttl, Some(60));
+
+        advance(Duration::from_secs(20)).await;
+        let hit = cache.get("example.com", QueryType::A).unwrap();
+        assert_eq!(hit.records, ["192.0.2.1"]);
+        assert_eq!(hit.ttl, Some(40));
+
+        advance(Duration::from_secs(41)).await;
+        assert!(cache.get("example.com", QueryType::A).is_none());
+        assert!(cache.entries.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn min_ttl_extends_short_answers() {
+        let cache = DnsCache::new(Duration::from_secs(120));
+        cache.insert("example.com", QueryType::A, &found(Some(60)));
+        cache.insert("example.org", QueryType::A, &found(Some(0)));
+        cache.insert("example.net", QueryType::A, &found(Some(300)));
+
+        advance(Duration::from_secs(90)).await;
+        assert_eq!(cache.get("example.com", QueryType::A).unwrap().ttl, Some(30));
+        assert_eq!(cache.get("example.org", QueryType::A).unwrap().ttl, Some(30));
+        assert_eq!(cache.get("example.net", QueryType::A).unwrap().ttl, Some(210));
+
+        advance(Duration::from_secs(31)).await;
+        assert!(cache.get("example.com", QueryType::A).is_none());
+        assert!(cache.get("example.net", QueryType::A).is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn answers_without_ttl_are_not_cached() {
+        let cache = DnsCache::new(Duration::ZERO);
+        cache.insert("example.com", QueryType::A, &found(Some(0)));
+        cache.insert("example.org", QueryType::A, &found(None));
+        assert!(cache.get("example.com", QueryType::A).is_none());
+        assert!(cache.get("example.org", QueryType::A).is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn entries_are_keyed_by_record_type() {
+        let cache = DnsCache::new(Duration::ZERO);
+        cache.insert("example.com", QueryType::A, &found(Some(60)));
+        assert!(cache.get("example.com", QueryType::A).is_some());
+        assert!(cache.get("example.com", QueryType::Mx).is_none());
+        assert!(cache.get("www.example.com", QueryType::A).is_none());
+    }
+}
@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use x509_parser::{extensions::GeneralName, prelude::*};
+
+/// Details of the certificate a server presented during an HTTPS probe,
+/// stored as JSON with `--capture-cert`.
+#[derive(Debug, Serialize)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    /// DNS names and IP addresses from the subject alternative name extension
+    pub sans: Vec<String>,
+    /// Hex serial number; together with the issuer it identifies the certificate
+    pub serial: String,
+    pub not_before: Option<DateTime<Utc>>,
+    pub not_after: Option<DateTime<Utc>>,
+}
+
+impl CertificateInfo {
+    /// Parse a DER-encoded X.509 certificate.
+    pub fn from_der(der: &[u8]) -> Option<Self> {
+        let (_, cert) = X509Certificate::from_der(der).ok()?;
+
+        let mut sans = Vec::new();
+        if let Ok(Some(extension)) = cert.subject_alternative_name() {
+            for name in &extension.value.general_names {
+                match name {
+                    GeneralName::DNSName(dns) => sans.push(dns.to_string()),
+                    GeneralName::IPAddress(bytes) => {
+                        if let Some(ip) = ip_from_bytes(bytes) {
+                            sans.push(ip);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let validity = cert.validity();
+        Some(CertificateInfo {
+            subject: cert.subject().to_string(),
+            issuer: cert.issuer().to_string(),
+            sans,
+            serial: cert.raw_serial_as_string(),
+            not_before: DateTime::from_timestamp(validity.not_before.timestamp(), 0),
+            not_after: DateTime::from_timestamp(validity.not_after.timestamp(), 0),
+        })
+    }
+}
+
+fn ip_from_bytes(bytes: &[u8]) -> Option<String> {
+    let ip: std::net::IpAddr = match bytes.len() {
+        4 => <[u8; 4]>::try_from(bytes).ok()?.into(),
+        16 => <[u8; 16]>::try_from(bytes).ok()?.into(),
+        _ => return None,
+    };
+    Some(ip.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A leaf certificate for example.com signed by a throwaway test CA.
+    const FIXTURE: &[u8] = include_bytes!("../tests/fixtures/cert.der");
+
+    #[test]
+    fn parses_certificate_fixture() {
+        let cert = CertificateInfo::from_der(FIXTURE).unwrap();
+        assert_eq!(cert.subject, "O=Example Corp, CN=example.com");
+        assert_eq!(cert.issuer, "C=US, O=Example Test CA, CN=Example Test Root");
+        // The email SAN is neither a DNS name nor an IP address, so it's left out
+        assert_eq!(cert.sans, ["example.com", "www.example.com", "192.0.2.1", "2001:db8::1"]);
+        assert_eq!(cert.serial, "01:23:45:67:89:ab:cd:ef");
+        assert_eq!(cert.not_before.unwrap().to_rfc3339(), "2024-01-01T00:00:00+00:00");
+        assert_eq!(cert.not_after.unwrap().to_rfc3339(), "2034-01-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn rejects_truncated_der() {
+        assert!(CertificateInfo::from_der(&FIXTURE[..FIXTURE.len() / 2]).is_none());
+        assert!(CertificateInfo::from_der(&[]).is_none());
+    }
+}
@@ -0,0 +1,141 @@
+use anyhow::{Context, Result};
+use clap::{parser::ValueSource, Arg, ArgAction, ArgMatches, Command};
+use std::ffi::OsString;
+
+/// Translate a TOML config file into command-line arguments so it goes
+/// through the same parsing and validation as flags do. Keys are flag names
+/// (`nameserver`) or argument names in either `snake_case` or `kebab-case`
+/// (`no_progress`, `no-progress`). Arguments already given on the command
+/// line, or conflicting with one that was, are left out, so precedence is
+/// defaults < file < CLI.
+pub fn config_args(path: &str, command: &Command, matches: &ArgMatches) -> Result<Vec<OsString>> {
+    let text = std::fs::read_to_string(path).context(format!("Failed to read config file: {}", path))?;
+    let table: toml::Table = text.parse().context(format!("Invalid config file: {}", path))?;
+
+    let mut args = Vec::new();
+    for (key, value) in table {
+        let name = key.replace('_', "-");
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_id() == key.replace('-', "_").as_str() || arg.get_long() == Some(&name))
+            .filter(|arg| arg.get_long().is_some() && arg.get_id() != "config")
+            .with_context(|| format!("Unknown key '{}' in config file {}", key, path))?;
+        let given = |arg: &Arg| matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine);
+        // Conflicts are declared on one side only, so check both directions
+        let conflicts_with_given = command.get_arg_conflicts_with(arg).into_iter().any(given)
+            || command.get_arguments().filter(|other| given(other)).any(|other| {
+                command.get_arg_conflicts_with(other).iter().any(|conflict| conflict.get_id() == arg.get_id())
+            });
+        if given(arg) || conflicts_with_given {
+            continue;
+        }
+        let flag = format!("--{}", arg.get_long().unwrap_or_default());
+
+        match (arg.get_action(), value) {
+            (ArgAction::SetTrue, toml::Value::Boolean(set)) => {
+                if set {
+                    args.push(flag.into());
+                }
+            }
+            (ArgAction::Count, toml::Value::Integer(count)) => {
+                for _ in 0..count {
+                    args.push(flag.clone().into());
+                }
+            }
+            (ArgAction::Append, toml::Value::Array(values)) => {
+                for value in values {
+                    args.push(format!("{}={}", flag, scalar(&key, value, path)?).into());
+                }
+            }
+            (ArgAction::Set | ArgAction::Append, value) => {
+                args.push(format!("{}={}", flag, scalar(&key, value, path)?).into());
+            }
+            (_, value) => anyhow::bail!(
+                "Unexpected {} for '{}' in config file {}",
+                value.type_str(),
+                key,
+                path
+            ),
+        }
+    }
+    Ok(args)
+}
+
+fn scalar(key: &str, value: toml::Value, path: &str) -> Result<String> {
+    match value {
+        toml::Value::String(value) => Ok(value),
+        toml::Value::Integer(value) => Ok(value.to_string()),
+        toml::Value::Float(value) => Ok(value.to_string()),
+        toml::Value::Boolean(value) => Ok(value.to_string()),
+        value => anyhow::bail!(
+            "Unexpected {} for '{}' in config file {}",
+            value.type_str(),
+            key,
+            path
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Args;
+    use clap::{CommandFactory, Parser};
+    use std::io::Write;
+
+    /// The arguments `config` contributes when `cli` is the command line.
+    fn args_for(config: &str, cli: &[&str]) -> Result<Vec<String>> {
+        let mut file = tempfile::NamedTempFile::new()?;
+        file.write_all(config.as_bytes())?;
+        let path = file.path().to_str().unwrap();
+        let matches = Args::command().try_get_matches_from(std::iter::once("dns_resolver").chain(cli.iter().copied()))?;
+        let args = config_args(path, &Args::command(), &matches)?;
+        Ok(args.into_iter().map(|arg| arg.into_string().unwrap()).collect())
+    }
+
+    #[test]
+    fn command_line_overrides_file() -> Result<()> {
+        let config = "concurrency = 3\nno_progress = true\nshuffle = false\ntimeout = 9\n";
+        assert_eq!(args_for(config, &[])?, ["--concurrency=3", "--no-progress", "--timeout=9"]);
+        assert_eq!(args_for(config, &["--timeout", "2"])?, ["--concurrency=3", "--no-progress"]);
+        Ok(())
+    }
+
+    #[test]
+    fn conflicting_command_line_overrides_file() -> Result<()> {
+        // --stdin declares the conflict with --input
+        assert!(args_for("input = \"hosts.txt\"\n", &["--stdin"])?.is_empty());
+        assert!(args_for("stdin = true\n", &["-i", "hosts.txt"])?.is_empty());
+        // --input-glob conflicts with both
+        let config = "input = \"hosts.txt\"\nstdin = true\ntimeout = 9\n";
+        assert_eq!(args_for(config, &["--input-glob", "hosts-*.txt"])?, ["--timeout=9"]);
+        assert_eq!(args_for("input_glob = \"hosts-*.txt\"\n", &["--stdin"])?, Vec::<String>::new());
+
+        let merged = ["dns_resolver", "--stdin"].into_iter().map(String::from);
+        let args = Args::try_parse_from(merged.chain(args_for("input = \"hosts.txt\"\n", &["--stdin"])?))?;
+        assert!(args.stdin);
+        assert_eq!(args.input, None);
+        Ok(())
+    }
+
+    #[test]
+    fn arrays_repeat_flags() -> Result<()> {
+        let config = "nameserver = [\"192.0.2.1\", \"192.0.2.2:5353\"]\nverbose = 2\n";
+        assert_eq!(
+            args_for(config, &[])?,
+            ["--nameserver=192.0.2.1", "--nameserver=192.0.2.2:5353", "--verbose", "--verbose"]
+        );
+        // A repeatable flag on the command line replaces the whole array
+        assert_eq!(args_for(config, &["--nameserver", "192.0.2.3"])?, ["--verbose", "--verbose"]);
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        for config in ["colour = true\n", "config = \"other.toml\"\n", "stdin = 1\n", "timeout = [1]\n"] {
+            assert!(args_for(config, &[]).is_err(), "{}", config);
+        }
+        let error = args_for("colour = true\n", &[]).unwrap_err();
+        assert!(error.to_string().starts_with("Unknown key 'colour'"), "{}", error);
+    }
+}
@@ -0,0 +1,159 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use rusqlite::{types::ValueRef, Connection};
+use serde_json::{Map, Value};
+use std::io::Write;
+
+/// Output formats supported by the `export` subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    /// One JSON object per line
+    Jsonl,
+}
+
+/// Tables that can be exported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportTable {
+    #[value(name = "dns_results")]
+    DnsResults,
+    Status,
+    /// Records from `--enumerate-records`
+    #[value(name = "dns_records")]
+    DnsRecords,
+    /// Mail exchanger addresses from `--follow-mx`
+    #[value(name = "mx_hosts")]
+    MxHosts,
+}
+
+impl ExportTable {
+    fn name(self) -> &'static str {
+        match self {
+            ExportTable::DnsResults => "dns_results",
+            ExportTable::Status => "status",
+            ExportTable::DnsRecords => "dns_records",
+            ExportTable::MxHosts => "mx_hosts",
+        }
+    }
+
+    /// Columns stored as 0/1 that are exported as JSON booleans.
+    fn bool_columns(self) -> &'static [&'static str] {
+        match self {
+            ExportTable::DnsResults => &["success", "wildcard"],
+            ExportTable::Status => &["redirected", "truncated"],
+            ExportTable::DnsRecords | ExportTable::MxHosts => &[],
+        }
+    }
+
+    /// Columns holding JSON text that is embedded rather than quoted.
+    fn json_columns(self) -> &'static [&'static str] {
+        match self {
+            ExportTable::DnsResults => &["records", "cname_chain"],
+            ExportTable::Status => &["headers", "certificate"],
+            ExportTable::DnsRecords | ExportTable::MxHosts => &[],
+        }
+    }
+}
+
+/// ASN columns grouped under `asn` in JSON exports of DNS results.
+const ASN_COLUMNS: &[&str] = &["asn", "as_name"];
+/// Geographic columns grouped under `geo` in JSON exports of DNS results.
+const GEO_COLUMNS: &[&str] = &["country", "country_code", "city", "org"];
+
+/// Write every row of `table` to `out` as CSV, headed by the table's column
+/// names. NULLs become empty fields and blobs are written as hex.
+pub fn export_csv<W: Write>(conn: &Connection, table: ExportTable, out: W) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {} ORDER BY id", table.name()))?;
+    let columns = stmt
+        .column_names()
+        .into_iter()
+        .map(String::from)
+        .collect::<Vec<_>>();
+
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(&columns)?;
+
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let mut record = Vec::with_capacity(columns.len());
+        for index in 0..columns.len() {
+            record.push(match row.get_ref(index)? {
+                ValueRef::Null => String::new(),
+                ValueRef::Integer(value) => value.to_string(),
+                ValueRef::Real(value) => value.to_string(),
+                ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned(),
+                ValueRef::Blob(bytes) => bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            });
+        }
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write every row of `table` to `out` as one JSON object per line, keyed by
+/// column name. Rows are streamed, so memory use doesn't depend on the size
+/// of the table. DNS results group their ASN and geographic columns into
+/// nested `asn` and `geo` objects, which are null when nothing was found.
+pub fn export_jsonl<W: Write>(conn: &Connection, table: ExportTable, mut out: W) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {} ORDER BY id", table.name()))?;
+    let columns = stmt
+        .column_names()
+        .into_iter()
+        .map(String::from)
+        .collect::<Vec<_>>();
+
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let mut object = Map::new();
+        for (index, column) in columns.iter().enumerate() {
+            let value = match row.get_ref(index)? {
+                ValueRef::Integer(value) if table.bool_columns().contains(&column.as_str()) => {
+                    Value::Bool(value != 0)
+                }
+                ValueRef::Text(text) if table.json_columns().contains(&column.as_str()) => {
+                    serde_json::from_slice(text)
+                        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(text).into_owned()))
+                }
+                ValueRef::Null => Value::Null,
+                ValueRef::Integer(value) => value.into(),
+                ValueRef::Real(value) => value.into(),
+                ValueRef::Text(text) => Value::String(String::from_utf8_lossy(text).into_owned()),
+                ValueRef::Blob(bytes) => bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            };
+            object.insert(column.clone(), value);
+        }
+
+        if table == ExportTable::DnsResults {
+            nest_columns(&mut object, "asn", ASN_COLUMNS);
+            nest_columns(&mut object, "geo", GEO_COLUMNS);
+        }
+
+        serde_json::to_writer(&mut out, &object)?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Move `columns` out of `object` into a nested object under `key`, placed
+/// where the first of them was, or null if every one of them is null or
+/// absent.
+fn nest_columns(object: &mut Map<String, Value>, key: &str, columns: &[&str]) {
+    let position = object
+        .keys()
+        .position(|column| columns.contains(&column.as_str()))
+        .unwrap_or(object.len());
+    let mut nested = Map::new();
+    for column in columns {
+        if let Some(value) = object.shift_remove(*column) {
+            nested.insert(column.to_string(), value);
+        }
+    }
+    let value = if nested.values().all(Value::is_null) {
+        Value::Null
+    } else {
+        Value::Object(nested)
+    };
+    object.shift_insert(position, key.to_string(), value);
+}
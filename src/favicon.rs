@@ -0,0 +1,90 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// Path fetched for each host with `--favicon-hash`.
+pub const FAVICON_PATH: &str = "/favicon.ico";
+
+/// Shodan-style favicon hash: MurmurHash3 (x86, 32-bit, seed 0) of the
+/// icon's base64 encoding, wrapped at 76 characters with a newline after
+/// every line as Python's `base64.encodebytes` does, read as a signed
+/// integer so it matches `http.favicon.hash` queries.
+pub fn favicon_hash(icon: &[u8]) -> i32 {
+    let encoded = STANDARD.encode(icon);
+    let mut wrapped = Vec::with_capacity(encoded.len() + encoded.len() / 76 + 1);
+    for line in encoded.as_bytes().chunks(76) {
+        wrapped.extend_from_slice(line);
+        wrapped.push(b'\n');
+    }
+    murmur3_32(&wrapped, 0) as i32
+}
+
+fn murmur3_32(data: &[u8], seed: u32) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+
+    let mut hash = seed;
+    let mut blocks = data.chunks_exact(4);
+    for block in &mut blocks {
+        let mut k = u32::from_le_bytes([block[0], block[1], block[2], block[3]]);
+        k = k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        hash ^= k;
+        hash = hash.rotate_left(13).wrapping_mul(5).wrapping_add(0xe654_6b64);
+    }
+
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        let mut k = 0u32;
+        for (i, &byte) in tail.iter().enumerate() {
+            k |= u32::from(byte) << (8 * i);
+        }
+        hash ^= k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+    }
+
+    hash ^= data.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^ (hash >> 16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn murmur3_matches_reference_vectors() {
+        // MurmurHash3_x86_32 vectors from the reference implementation
+        assert_eq!(murmur3_32(b"", 0), 0);
+        assert_eq!(murmur3_32(b"", 1), 0x514e_28b7);
+        assert_eq!(murmur3_32(b"", 0xffff_ffff), 0x81f1_6f39);
+        assert_eq!(murmur3_32(&[0xff, 0xff, 0xff, 0xff], 0), 0x7629_3b50);
+        assert_eq!(murmur3_32(&[0x21, 0x43, 0x65, 0x87], 0), 0xf55b_516b);
+        assert_eq!(murmur3_32(&[0x21, 0x43, 0x65, 0x87], 0x5082_edee), 0x2362_f9de);
+        assert_eq!(murmur3_32(&[0x21, 0x43, 0x65], 0), 0x7e4a_8634);
+        assert_eq!(murmur3_32(&[0x21, 0x43], 0), 0xa0f7_b07a);
+        assert_eq!(murmur3_32(&[0x21], 0), 0x7266_1cf4);
+        assert_eq!(murmur3_32(&[0, 0, 0, 0], 0), 0x2362_f9de);
+        assert_eq!(murmur3_32(b"Hello, world!", 1234), 0xfaf6_cdb3);
+        assert_eq!(murmur3_32(b"The quick brown fox jumps over the lazy dog", 0x9747_b28c), 0x2fa8_26cd);
+        // Python's mmh3.hash("foo"), which Shodan's hash is read like
+        assert_eq!(murmur3_32(b"foo", 0) as i32, -156_908_512);
+    }
+
+    #[test]
+    fn icon_is_wrapped_like_python_encodebytes() {
+        // Expected encodings from Python's base64.encodebytes
+        let icon: Vec<u8> = (0..100).chain(0..100).collect();
+        let encoded = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8gISIjJCUmJygpKissLS4vMDEyMzQ1Njc4\n\
+            OTo7PD0+P0BBQkNERUZHSElKS0xNTk9QUVJTVFVWV1hZWltcXV5fYGFiYwABAgMEBQYHCAkKCwwN\n\
+            Dg8QERITFBUWFxgZGhscHR4fICEiIyQlJicoKSorLC0uLzAxMjM0NTY3ODk6Ozw9Pj9AQUJDREVG\n\
+            R0hJSktMTU5PUFFSU1RVVldYWVpbXF1eX2BhYmM=\n";
+        assert_eq!(favicon_hash(&icon), murmur3_32(encoded.as_bytes(), 0) as i32);
+
+        // A body filling exactly one line still gets a single newline
+        let icon: Vec<u8> = (0..57).collect();
+        let encoded = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8gISIjJCUmJygpKissLS4vMDEyMzQ1Njc4\n";
+        assert_eq!(favicon_hash(&icon), murmur3_32(encoded.as_bytes(), 0) as i32);
+
+        assert_eq!(favicon_hash(b""), 0);
+    }
+}
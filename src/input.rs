@@ -0,0 +1,364 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    net::IpAddr,
+    path::PathBuf,
+};
+
+/// Formats of the `--input` host list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    /// One `hostname` or `hostname,tag` per line
+    Text,
+    /// CSV with a header row naming a `hostname` and optionally a `tag` column
+    Csv,
+    /// A JSON array of objects with a `hostname` and optionally a `tag` field
+    Json,
+}
+
+impl InputFormat {
+    /// Guess the format from a file extension, defaulting to text.
+    pub fn from_path(path: &str) -> Self {
+        let extension = path.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("csv") => InputFormat::Csv,
+            Some("json") => InputFormat::Json,
+            _ => InputFormat::Text,
+        }
+    }
+}
+
+/// A host as listed in the input, before it is validated and normalized.
+#[derive(Debug, Clone)]
+pub struct InputEntry {
+    pub host: String,
+    pub tag: Option<String>,
+}
+
+impl InputEntry {
+    /// A bare hostname, e.g. one expanded from `--pattern`.
+    pub fn host(host: String) -> Self {
+        InputEntry { host, tag: None }
+    }
+
+    /// A `hostname` or `hostname,tag` text line.
+    fn from_line(line: &str) -> Self {
+        match line.split_once(',') {
+            Some((host, tag)) => InputEntry {
+                host: host.to_string(),
+                tag: Some(tag.trim().to_string()).filter(|tag| !tag.is_empty()),
+            },
+            None => InputEntry::host(line.to_string()),
+        }
+    }
+}
+
+/// Read host entries from `reader` in `format`. Text and CSV are streamed;
+/// a JSON array is parsed in full first.
+pub fn read_entries<R: BufRead + 'static>(
+    reader: R,
+    format: InputFormat,
+) -> Result<Box<dyn Iterator<Item = InputEntry>>> {
+    Ok(match format {
+        InputFormat::Text => Box::new(read_hostnames(reader).map(|line| InputEntry::from_line(&line))),
+        InputFormat::Csv => Box::new(read_csv(reader)?),
+        InputFormat::Json => Box::new(read_json(reader)?.into_iter()),
+    })
+}
+
+/// Files matching a glob pattern such as `hosts-*.txt`, in alphabetical
+/// order. Fails when nothing matches.
+pub fn expand_glob(pattern: &str) -> Result<Vec<PathBuf>> {
+    let paths = glob::glob(pattern)
+        .with_context(|| format!("Invalid input glob: {}", pattern))?
+        .collect::<Result<Vec<_>, _>>()?;
+    if paths.is_empty() {
+        anyhow::bail!("No input files match {}", pattern);
+    }
+    Ok(paths)
+}
+
+/// Read host entries from each of `paths` in turn, logging how many entries
+/// a file held once it is read. Every file is opened up front so a bad one
+/// fails before any lookups start. Without `format`, each file's format is
+/// guessed from its extension.
+pub fn read_files(
+    paths: Vec<PathBuf>,
+    format: Option<InputFormat>,
+) -> Result<Box<dyn Iterator<Item = InputEntry>>> {
+    let mut files = Vec::with_capacity(paths.len());
+    for path in paths {
+        let path = path.display().to_string();
+        let file = File::open(&path).with_context(|| format!("Failed to open input file: {}", path))?;
+        let format = format.unwrap_or_else(|| InputFormat::from_path(&path));
+        let entries = read_entries(BufReader::new(file), format)
+            .with_context(|| format!("Failed to read input file: {}", path))?;
+        files.push((path, entries));
+    }
+
+    Ok(Box::new(files.into_iter().flat_map(|(path, mut entries)| {
+        let mut count = Some(0);
+        std::iter::from_fn(move || match entries.next() {
+            Some(entry) => {
+                count = count.map(|count| count + 1);
+                Some(entry)
+            }
+            None => {
+                if let Some(count) = count.take() {
+                    tracing::info!("Read {} entries from {}", count, path);
+                }
+                None
+            }
+        })
+    })))
+}
+
+/// Lazily yield `word.base_domain` for each word in `reader`, one per line.
+/// Blank lines and `#` comments are skipped as in a host list.
+pub fn read_wordlist<R: BufRead>(reader: R, base_domain: &str) -> impl Iterator<Item = InputEntry> {
+    let base_domain = base_domain.trim_matches('.').to_string();
+    read_hostnames(reader)
+        .map(move |word| InputEntry::host(format!("{}.{}", word.trim_end_matches('.'), base_domain)))
+}
+
+/// Lazily read hostnames from `reader`, one per line, trimming whitespace
+/// and skipping blank lines and `#` comments. Reading stops at the first
+/// I/O error.
+fn read_hostnames<R: BufRead>(reader: R) -> impl Iterator<Item = String> {
+    reader
+        .lines()
+        .map_while(|line| match line {
+            Ok(line) => Some(line),
+            Err(e) => {
+                tracing::error!("Failed to read hostnames: {}", e);
+                None
+            }
+        })
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+/// Lazily read entries from CSV with a header row. The `hostname` and `tag`
+/// columns are found by name, ignoring case; other columns are ignored.
+/// Reading stops at the first malformed row.
+fn read_csv<R: Read>(reader: R) -> Result<impl Iterator<Item = InputEntry>> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).trim(csv::Trim::All).from_reader(reader);
+    let headers = reader.headers().context("Failed to read CSV header")?;
+    let column = |name: &str| headers.iter().position(|header| header.eq_ignore_ascii_case(name));
+    let host_column = column("hostname").context("CSV input has no 'hostname' column")?;
+    let tag_column = column("tag");
+
+    Ok(reader
+        .into_records()
+        .map_while(|record| match record {
+            Ok(record) => Some(record),
+            Err(e) => {
+                tracing::error!("Failed to read hostnames: {}", e);
+                None
+            }
+        })
+        .map(move |record| InputEntry {
+            host: record.get(host_column).unwrap_or_default().to_string(),
+            tag: tag_column
+                .and_then(|column| record.get(column))
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string),
+        })
+        .filter(|entry| !entry.host.is_empty() || entry.tag.is_some()))
+}
+
+/// Read entries from a JSON array of objects with a `hostname` string and
+/// an optional `tag`; other fields are ignored. Plain strings in the array
+/// are taken as hostnames.
+fn read_json<R: Read>(reader: R) -> Result<Vec<InputEntry>> {
+    let items: Vec<Value> = serde_json::from_reader(reader).context("Invalid JSON input: expected an array")?;
+    let entries = items
+        .into_iter()
+        .map(|item| match item {
+            Value::String(host) => InputEntry::host(host),
+            item => InputEntry {
+                host: item["hostname"].as_str().unwrap_or_default().to_string(),
+                tag: match &item["tag"] {
+                    Value::Null => None,
+                    Value::String(tag) => Some(tag.clone()),
+                    tag => Some(tag.to_string()),
+                },
+            },
+        })
+        .collect();
+    Ok(entries)
+}
+
+/// Read `/etc/hosts`-style `ip hostname [alias...]` lines into a map from
+/// lowercase hostname to its addresses, in file order. Blank lines and `#`
+/// comments are skipped.
+pub fn read_hosts_file<R: BufRead>(reader: R) -> Result<HashMap<String, Vec<IpAddr>>> {
+    let mut hosts: HashMap<String, Vec<IpAddr>> = HashMap::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let Some(ip) = fields.next() else { continue };
+        let ip: IpAddr = ip
+            .parse()
+            .with_context(|| format!("Invalid address '{}' on line {}", ip, number + 1))?;
+        for name in fields {
+            let name = name.trim_end_matches('.').to_ascii_lowercase();
+            hosts.entry(name).or_default().push(ip);
+        }
+    }
+    Ok(hosts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn entries(input: &str, format: InputFormat) -> Result<Vec<(String, Option<String>)>> {
+        let entries = read_entries(Cursor::new(input.to_string()), format)?;
+        Ok(entries.map(|entry| (entry.host, entry.tag)).collect())
+    }
+
+    fn entry(host: &str, tag: Option<&str>) -> (String, Option<String>) {
+        (host.to_string(), tag.map(str::to_string))
+    }
+
+    #[test]
+    fn text_lines_take_optional_tags() -> Result<()> {
+        let input = "# hosts\nexample.com\n\n  www.example.com , web \napi.example.com,\n";
+        assert_eq!(
+            entries(input, InputFormat::Text)?,
+            [
+                entry("example.com", None),
+                entry("www.example.com ", Some("web")),
+                entry("api.example.com", None),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn csv_columns_are_found_by_name() -> Result<()> {
+        let input = "Rank,HostName,TAG\n1,example.com,prod\n2,example.org,\n3,,orphan\n";
+        assert_eq!(
+            entries(input, InputFormat::Csv)?,
+            [
+                entry("example.com", Some("prod")),
+                entry("example.org", None),
+                entry("", Some("orphan")),
+            ]
+        );
+        let input = "hostname\nexample.com\n";
+        assert_eq!(entries(input, InputFormat::Csv)?, [entry("example.com", None)]);
+        Ok(())
+    }
+
+    #[test]
+    fn csv_without_hostname_column_fails() {
+        assert!(entries("host,tag\nexample.com,prod\n", InputFormat::Csv).is_err());
+    }
+
+    #[test]
+    fn json_takes_strings_and_objects() -> Result<()> {
+        let input = r#"["example.com", {"hostname": "example.org", "tag": "prod", "rank": 1},
+            {"hostname": "example.net", "tag": 7}, {"tag": "orphan"}]"#;
+        assert_eq!(
+            entries(input, InputFormat::Json)?,
+            [
+                entry("example.com", None),
+                entry("example.org", Some("prod")),
+                entry("example.net", Some("7")),
+                entry("", Some("orphan")),
+            ]
+        );
+        assert!(entries(r#"{"hostname": "example.com"}"#, InputFormat::Json).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn format_is_guessed_from_extension() {
+        assert_eq!(InputFormat::from_path("hosts.CSV"), InputFormat::Csv);
+        assert_eq!(InputFormat::from_path("hosts.json"), InputFormat::Json);
+        assert_eq!(InputFormat::from_path("hosts.txt"), InputFormat::Text);
+        assert_eq!(InputFormat::from_path("hosts"), InputFormat::Text);
+    }
+    #[test]
+    fn hosts_file_maps_names_and_aliases() -> Result<()> {
+        let input = "# local overrides\n\
+            127.0.0.1 localhost\n\
+            192.0.2.10  Www.Example.com. example.com   # web\n\
+            \n\
+            2001:db8::10 www.example.com\n\
+            192.0.2.11 api.example.com#inline\n";
+        let hosts = read_hosts_file(Cursor::new(input))?;
+        let ips = |name: &str| hosts[name].iter().map(IpAddr::to_string).collect::<Vec<_>>();
+        assert_eq!(hosts.len(), 4);
+        assert_eq!(ips("localhost"), ["127.0.0.1"]);
+        assert_eq!(ips("www.example.com"), ["192.0.2.10", "2001:db8::10"]);
+        assert_eq!(ips("example.com"), ["192.0.2.10"]);
+        assert_eq!(ips("api.example.com"), ["192.0.2.11"]);
+        Ok(())
+    }
+
+    #[test]
+    fn hosts_file_rejects_invalid_addresses() {
+        let error = read_hosts_file(Cursor::new("127.0.0.1 localhost\n300.1.2.3 bad.example.com\n")).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid address '300.1.2.3' on line 2");
+        assert!(read_hosts_file(Cursor::new("example.com 192.0.2.1\n")).is_err());
+    }
+    /// A directory with host lists in each format, plus a file the
+    /// `hosts-*` pattern doesn't match.
+    fn host_lists() -> std::io::Result<tempfile::TempDir> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("hosts-b.txt"), "b1.example.com\nb2.example.com\n")?;
+        std::fs::write(dir.path().join("hosts-a.csv"), "hostname,tag\na.example.com,prod\n")?;
+        std::fs::write(dir.path().join("hosts-c.json"), r#"["c.example.com"]"#)?;
+        std::fs::write(dir.path().join("other.txt"), "other.example.com\n")?;
+        Ok(dir)
+    }
+
+    #[test]
+    fn glob_reads_matching_files_in_order() -> Result<()> {
+        let dir = host_lists()?;
+        let pattern = format!("{}/hosts-*", dir.path().display());
+        let paths = expand_glob(&pattern)?;
+        let names: Vec<_> = paths.iter().map(|path| path.file_name().unwrap().to_string_lossy()).collect();
+        assert_eq!(names, ["hosts-a.csv", "hosts-b.txt", "hosts-c.json"]);
+
+        // Each file's format comes from its extension
+        let entries: Vec<_> = read_files(paths, None)?.map(|entry| (entry.host, entry.tag)).collect();
+        assert_eq!(
+            entries,
+            [
+                entry("a.example.com", Some("prod")),
+                entry("b1.example.com", None),
+                entry("b2.example.com", None),
+                entry("c.example.com", None),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn glob_format_override_applies_to_every_file() -> Result<()> {
+        let dir = host_lists()?;
+        let paths = expand_glob(&format!("{}/hosts-*.csv", dir.path().display()))?;
+        let entries: Vec<_> = read_files(paths, Some(InputFormat::Text))?.map(|entry| entry.host).collect();
+        assert_eq!(entries, ["hostname", "a.example.com"]);
+        Ok(())
+    }
+
+    #[test]
+    fn glob_without_matches_fails() -> Result<()> {
+        let dir = host_lists()?;
+        let error = expand_glob(&format!("{}/nope-*.txt", dir.path().display())).unwrap_err();
+        assert!(error.to_string().starts_with("No input files match"), "{}", error);
+        assert!(expand_glob("hosts-[.txt").is_err());
+        Ok(())
+    }
+}
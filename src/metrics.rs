@@ -0,0 +1,196 @@
+use anyhow::{Context, Result};
+use prometheus::{
+    exponential_buckets, Encoder, Histogram, HistogramOpts, IntCounter, IntGauge, Registry, TextEncoder,
+};
+use std::net::SocketAddr;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{DnsErrorKind, DnsResult, HttpResult};
+
+/// Counts one task in an in-flight gauge for as long as it is held. Tasks
+/// keep it in their future, so one that is aborted or dropped at shutdown
+/// still leaves the gauge.
+#[must_use]
+pub struct InFlight(IntGauge);
+
+impl InFlight {
+    fn new(gauge: &IntGauge) -> Self {
+        gauge.inc();
+        InFlight(gauge.clone())
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+/// Prometheus metrics for a run, updated by the main loop as results come
+/// in and served in the text exposition format with `--metrics-addr`.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    dns_resolved: IntCounter,
+    dns_failed: IntCounter,
+    dns_timeouts: IntCounter,
+    dns_duration: Histogram,
+    dns_in_flight: IntGauge,
+    http_completed: IntCounter,
+    http_errors: IntCounter,
+    http_duration: Histogram,
+    http_in_flight: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new();
+        // 5 ms up to about 20 seconds
+        let buckets = exponential_buckets(0.005, 2.0, 13)?;
+
+        let metrics = Metrics {
+            dns_resolved: IntCounter::new("dns_resolved_total", "Lookups that returned records")?,
+            dns_failed: IntCounter::new("dns_failed_total", "Lookups that failed, timeouts included")?,
+            dns_timeouts: IntCounter::new("dns_timeouts_total", "Lookups that timed out")?,
+            dns_duration: Histogram::with_opts(
+                HistogramOpts::new("dns_duration_seconds", "Time spent resolving each host")
+                    .buckets(buckets.clone()),
+            )?,
+            dns_in_flight: IntGauge::new("dns_in_flight", "Lookups currently running")?,
+            http_completed: IntCounter::new("http_requests_total", "HTTP probes completed")?,
+            http_errors: IntCounter::new("http_errors_total", "HTTP probes that got no response")?,
+            http_duration: Histogram::with_opts(
+                HistogramOpts::new("http_duration_seconds", "Time spent on each HTTP probe").buckets(buckets),
+            )?,
+            http_in_flight: IntGauge::new("http_in_flight", "HTTP probes currently running")?,
+            registry,
+        };
+        metrics.registry.register(Box::new(metrics.dns_resolved.clone()))?;
+        metrics.registry.register(Box::new(metrics.dns_failed.clone()))?;
+        metrics.registry.register(Box::new(metrics.dns_timeouts.clone()))?;
+        metrics.registry.register(Box::new(metrics.dns_duration.clone()))?;
+        metrics.registry.register(Box::new(metrics.dns_in_flight.clone()))?;
+        metrics.registry.register(Box::new(metrics.http_completed.clone()))?;
+        metrics.registry.register(Box::new(metrics.http_errors.clone()))?;
+        metrics.registry.register(Box::new(metrics.http_duration.clone()))?;
+        metrics.registry.register(Box::new(metrics.http_in_flight.clone()))?;
+        Ok(metrics)
+    }
+
+    /// Count a lookup as running until the returned guard is dropped.
+    pub fn dns_started(&self) -> InFlight {
+        InFlight::new(&self.dns_in_flight)
+    }
+
+    pub fn record_dns(&self, result: &DnsResult) {
+        self.dns_duration.observe(result.dns_ms as f64 / 1000.0);
+        if !result.records.is_empty() {
+            self.dns_resolved.inc();
+        } else if !result.success {
+            self.dns_failed.inc();
+            if result.error_kind == Some(DnsErrorKind::Timeout) {
+                self.dns_timeouts.inc();
+            }
+        }
+    }
+
+    /// Count a probe as running until the returned guard is dropped.
+    pub fn http_started(&self) -> InFlight {
+        InFlight::new(&self.http_in_flight)
+    }
+
+    pub fn record_http(&self, result: &HttpResult) {
+        self.http_completed.inc();
+        self.http_duration.observe(result.http_ms as f64 / 1000.0);
+        if result.error.is_some() {
+            self.http_errors.inc();
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!("Failed to encode metrics: {}", e);
+        }
+        buffer
+    }
+
+    /// Serve the metrics on `addr` until the process exits. Every request
+    /// gets the current metrics, whatever its path.
+    pub async fn serve(self, addr: SocketAddr) -> Result<()> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .context(format!("Failed to listen for metrics on {}", addr))?;
+        tracing::info!("Serving metrics on http://{}/metrics", listener.local_addr()?);
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let metrics = self.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = metrics.respond(stream).await {
+                                tracing::debug!("Metrics request failed: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => tracing::debug!("Failed to accept metrics connection: {}", e),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    async fn respond(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        // The request itself is irrelevant; read it so the client isn't reset
+        let mut request = [0; 1024];
+        let _ = stream.read(&mut request).await?;
+
+        let body = self.encode();
+        let header = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            TextEncoder::new().format_type(),
+            body.len()
+        );
+        stream.write_all(header.as_bytes()).await?;
+        stream.write_all(&body).await?;
+        stream.shutdown().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::task::JoinSet;
+
+    #[tokio::test]
+    async fn finished_tasks_leave_the_gauge() {
+        let metrics = Metrics::new().unwrap();
+        let in_flight = metrics.http_started();
+        let task = tokio::spawn(async move {
+            let _in_flight = in_flight;
+        });
+        task.await.unwrap();
+        assert_eq!(metrics.http_in_flight.get(), 0);
+    }
+
+    #[tokio::test]
+    async fn aborted_tasks_leave_the_gauge() {
+        let metrics = Metrics::new().unwrap();
+        let mut lookups = JoinSet::new();
+        for _ in 0..3 {
+            let in_flight = metrics.dns_started();
+            lookups.spawn(async move {
+                let _in_flight = in_flight;
+                std::future::pending::<()>().await
+            });
+        }
+        assert_eq!(metrics.dns_in_flight.get(), 3);
+
+        lookups.abort_all();
+        while lookups.join_next().await.is_some() {}
+        assert_eq!(metrics.dns_in_flight.get(), 0);
+    }
+}
@@ -0,0 +1,91 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::{io::Write, time::Duration};
+
+/// Progress display for a run: one bar for DNS lookups and one for HTTP
+/// probes. When disabled the bars are hidden, so callers don't need to check.
+#[derive(Clone)]
+pub struct Progress {
+    multi: MultiProgress,
+}
+
+impl Progress {
+    pub fn new(enabled: bool) -> Self {
+        let multi = if enabled {
+            MultiProgress::new()
+        } else {
+            MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+        };
+        Progress { multi }
+    }
+
+    /// Add the DNS bar. Without a known total it shows a count and rate
+    /// instead of a bar and ETA.
+    pub fn dns_bar(&self, total: Option<usize>) -> ProgressBar {
+        let bar = match total {
+            Some(total) => ProgressBar::new(total as u64).with_style(
+                ProgressStyle::with_template(
+                    "DNS  [{bar:40}] {pos}/{len} ({per_sec}, ETA {eta})",
+                )
+                .expect("valid progress template")
+                .progress_chars("=> "),
+            ),
+            None => ProgressBar::new_spinner().with_style(
+                ProgressStyle::with_template("DNS  {spinner} {pos} ({per_sec})")
+                    .expect("valid progress template"),
+            ),
+        };
+        self.add(bar)
+    }
+
+    /// Add the HTTP bar. Its length grows as resolved hosts are queued.
+    pub fn http_bar(&self) -> ProgressBar {
+        let bar = ProgressBar::new(0).with_style(
+            ProgressStyle::with_template("HTTP [{bar:40}] {pos}/{len} ({per_sec}, ETA {eta})")
+                .expect("valid progress template")
+                .progress_chars("=> "),
+        );
+        self.add(bar)
+    }
+
+    fn add(&self, bar: ProgressBar) -> ProgressBar {
+        let bar = self.multi.add(bar);
+        bar.enable_steady_tick(Duration::from_millis(100));
+        bar
+    }
+
+    /// A writer for tracing that prints each event above the bars.
+    pub fn log_writer(&self) -> LogWriter {
+        LogWriter {
+            multi: self.multi.clone(),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+/// Buffers one tracing event and writes it to stdout with the progress bars
+/// suspended, so log lines don't get drawn over.
+pub struct LogWriter {
+    multi: MultiProgress,
+    buffer: Vec<u8>,
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let buffer = std::mem::take(&mut self.buffer);
+        self.multi.suspend(|| std::io::stdout().write_all(&buffer))
+    }
+}
+
+impl Drop for LogWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
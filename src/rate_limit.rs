@@ -0,0 +1,108 @@
+use rand::Rng;
+use std::time::Duration;
+use tokio::{sync::Mutex, time::Instant};
+
+/// Spaces out operations to at most `rate` per second across every task
+/// sharing the limiter, regardless of how many run concurrently. There is no
+/// burst allowance: each caller gets the next free slot.
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64) -> Self {
+        RateLimiter {
+            interval: Duration::from_secs_f64(1.0 / rate),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait for this caller's slot.
+    pub async fn acquire(&self) {
+        let slot = {
+            let mut next = self.next.lock().await;
+            let slot = (*next).max(Instant::now());
+            *next = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+/// A random pause between `min` and `max` taken before each request, so
+/// concurrent workers don't fire in lockstep.
+#[derive(Debug, Clone, Copy)]
+pub struct Jitter {
+    min: Duration,
+    max: Duration,
+}
+
+impl Jitter {
+    pub fn new(min: Duration, max: Duration) -> Self {
+        Jitter { min, max }
+    }
+
+    pub async fn sleep(&self) {
+        let delay = rand::thread_rng().gen_range(self.min..=self.max);
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test(start_paused = true)]
+    async fn acquires_are_spaced_at_the_rate() {
+        let limiter = Arc::new(RateLimiter::new(10.0));
+        let start = Instant::now();
+        let tasks: Vec<_> = (0..25)
+            .map(|_| {
+                let limiter = Arc::clone(&limiter);
+                tokio::spawn(async move {
+                    limiter.acquire().await;
+                    start.elapsed()
+                })
+            })
+            .collect();
+        let mut elapsed = Vec::new();
+        for task in tasks {
+            elapsed.push(task.await.unwrap());
+        }
+        elapsed.sort();
+
+        // One slot every 100ms, the first right away
+        for (i, elapsed) in elapsed.iter().enumerate() {
+            assert_eq!(*elapsed, Duration::from_millis(100 * i as u64), "acquire {}", i);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_time_does_not_build_a_burst() {
+        let limiter = RateLimiter::new(4.0);
+        limiter.acquire().await;
+        tokio::time::sleep(Duration::from_secs(5)).await;
+
+        let start = Instant::now();
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_millis(500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn jitter_stays_within_bounds() {
+        let jitter = Jitter::new(Duration::from_millis(50), Duration::from_millis(80));
+        for _ in 0..20 {
+            let start = Instant::now();
+            jitter.sleep().await;
+            // Timers fire on whole milliseconds, so allow for rounding up
+            let elapsed = start.elapsed();
+            assert!(elapsed >= Duration::from_millis(50) && elapsed <= Duration::from_millis(81), "{:?}", elapsed);
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+use crate::{
+    build_asn_cache, build_http_client, build_resolver, check_http_endpoint, probe_options,
+    resolve_hostname, resolve_options, Args, ProbeTarget, QueryType, Scheme, SelftestArgs,
+};
+
+/// Check each stage of a run against a known-good host with the configured
+/// resolver, ASN providers and HTTP settings: resolve it, look up the ASN of
+/// its address and probe it over HTTPS. Prints PASS or FAIL per stage and
+/// fails if any stage did.
+pub async fn run_selftest(args: &Args, selftest: &SelftestArgs) -> Result<()> {
+    let host = &selftest.host;
+    let mut failed = 0;
+
+    let resolver = build_resolver(args, false).await?;
+    let mut options = resolve_options(args);
+    // ASN enrichment needs an address whatever --record-type says
+    options.record_type = QueryType::A;
+    let start = Instant::now();
+    let mut result = resolve_hostname(host.clone(), &resolver, &options).await;
+    let outcome = match (&result.ip_address, &result.error) {
+        (Some(ip), _) => Ok(format!("{} -> {}", host, ip)),
+        (None, Some(error)) => Err(format!("{}: {}", host, error)),
+        (None, None) => Err(format!("{}: no addresses", host)),
+    };
+    failed += report("DNS", start.elapsed(), outcome);
+
+    let asn_cache = build_asn_cache(args)?;
+    let start = Instant::now();
+    let outcome = if let Some(ip) = result.ip_address.clone() {
+        asn_cache.enrich(std::slice::from_mut(&mut result)).await;
+        match (&result.asn, &result.as_name) {
+            (Some(asn), Some(as_name)) => Ok(format!("{} -> {} {}", ip, asn, as_name)),
+            _ => Err(format!("{}: no ASN details from any provider", ip)),
+        }
+    } else {
+        Err("skipped: DNS stage found no address".to_string())
+    };
+    failed += report("ASN", start.elapsed(), outcome);
+
+    let client = build_http_client(args)?;
+    let mut probe = probe_options(args)?;
+    probe.scheme = Scheme::Https;
+    let target = ProbeTarget {
+        hostname: host.clone(),
+        ip: result.ip_address.as_deref().and_then(|ip| ip.parse().ok()),
+    };
+    let start = Instant::now();
+    let http = check_http_endpoint(&client, &target, None, "/", &probe).await;
+    let outcome = match &http.error {
+        None => Ok(format!("https://{}/ -> {}", host, http.status_code)),
+        Some(error) => Err(format!("https://{}/: {}", host, error)),
+    };
+    failed += report("HTTPS", start.elapsed(), outcome);
+
+    if failed > 0 {
+        anyhow::bail!("Self-test failed: {} of 3 stages failed", failed);
+    }
+    Ok(())
+}
+
+/// Print one stage's result and return 1 if it failed.
+fn report(stage: &str, elapsed: Duration, outcome: Result<String, String>) -> usize {
+    let (status, detail, failed) = match outcome {
+        Ok(detail) => ("PASS", detail, 0),
+        Err(detail) => ("FAIL", detail, 1),
+    };
+    println!("{} {:<5} {:>6} ms  {}", status, stage, elapsed.as_millis(), detail);
+    failed
+}
@@ -0,0 +1,37 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashSet;
+
+use crate::{DnsResult, HttpResult, QueryType};
+
+/// Where a run's results are written. Writes may be buffered in an open
+/// transaction until `commit`, which the main loop calls periodically and
+/// once more before exiting.
+#[async_trait]
+pub trait ResultStore: Send {
+    /// Create or migrate the schema; safe to run against an existing store.
+    async fn init(&mut self) -> Result<()>;
+
+    /// Delete every stored result, for `--fresh`. Called after `init`.
+    async fn clear(&mut self) -> Result<()>;
+
+    /// Record the start of run `run_id` in the `runs` table with the command
+    /// line it was started with. Every row saved afterwards carries its id.
+    async fn begin_run(&mut self, run_id: &str, args: &[String]) -> Result<()>;
+
+    /// Record the end of the current run and how many hostnames it
+    /// processed. Does nothing if no run was begun.
+    async fn finish_run(&mut self, hostnames: usize) -> Result<()>;
+
+    async fn save_result(&mut self, result: &DnsResult) -> Result<()>;
+
+    async fn save_http_result(&mut self, result: &HttpResult) -> Result<()>;
+
+    /// Hostnames that already have a result of `record_type`, for `--resume`.
+    async fn resolved_hostnames(&mut self, record_type: QueryType) -> Result<HashSet<String>>;
+
+    /// Whether any writes are waiting on `commit`.
+    fn has_pending(&self) -> bool;
+
+    async fn commit(&mut self) -> Result<()>;
+}
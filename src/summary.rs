@@ -0,0 +1,184 @@
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    time::Duration,
+};
+
+use crate::{DnsResult, HttpResult};
+
+/// Number of ASNs listed in the summary.
+const TOP_ASNS: usize = 10;
+
+/// Running totals for the end-of-run report, updated as results complete
+/// so nothing beyond the set of unique IPs has to be kept in memory.
+#[derive(Debug, Default)]
+pub struct RunSummary {
+    hostnames: usize,
+    invalid: usize,
+    resolved: usize,
+    error_kinds: BTreeMap<&'static str, usize>,
+    unique_ips: HashSet<String>,
+    asn_hosts: HashMap<String, usize>,
+    http_completed: usize,
+    http_errors: usize,
+    http_interesting: usize,
+    status_codes: BTreeMap<u16, usize>,
+    tcp_states: BTreeMap<&'static str, usize>,
+}
+
+impl RunSummary {
+    pub fn record_dns(&mut self, result: &DnsResult) {
+        self.hostnames += 1;
+        if !result.records.is_empty() {
+            self.resolved += 1;
+        } else if !result.success {
+            let kind = result.error_kind.map_or("other", |kind| kind.as_str());
+            *self.error_kinds.entry(kind).or_default() += 1;
+        }
+
+        if result.record_type.is_address() {
+            self.unique_ips.extend(result.records.iter().cloned());
+        }
+        if let Some(asn) = &result.asn {
+            *self.asn_hosts.entry(asn.clone()).or_default() += 1;
+        }
+    }
+
+    /// Count input entries skipped because they aren't valid hostnames.
+    pub fn record_invalid(&mut self, count: usize) {
+        self.invalid += count;
+    }
+
+    /// Count a finished probe; `interesting` responses match `--interesting-status`.
+    pub fn record_http(&mut self, result: &HttpResult, interesting: bool) {
+        self.http_completed += 1;
+        if interesting {
+            self.http_interesting += 1;
+        }
+        if let Some(state) = result.tcp_state {
+            *self.tcp_states.entry(state.as_str()).or_default() += 1;
+        } else if result.error.is_some() {
+            self.http_errors += 1;
+        } else {
+            *self.status_codes.entry(result.status_code).or_default() += 1;
+        }
+    }
+
+    /// ASNs ordered by how many hosts resolved into them, ties broken by name.
+    fn top_asns(&self) -> Vec<AsnCount> {
+        let mut asns = self
+            .asn_hosts
+            .iter()
+            .map(|(asn, &hosts)| AsnCount {
+                asn: asn.clone(),
+                hosts,
+            })
+            .collect::<Vec<_>>();
+        asns.sort_by(|a, b| b.hosts.cmp(&a.hosts).then_with(|| a.asn.cmp(&b.asn)));
+        asns.truncate(TOP_ASNS);
+        asns
+    }
+
+    pub fn report(&self, elapsed: Duration) -> SummaryReport {
+        SummaryReport {
+            elapsed_secs: elapsed.as_secs_f64(),
+            hostnames: self.hostnames,
+            invalid: self.invalid,
+            resolved: self.resolved,
+            failed: self.error_kinds.values().sum(),
+            error_kinds: self.error_kinds.clone(),
+            unique_ips: self.unique_ips.len(),
+            top_asns: self.top_asns(),
+            http: HttpSummary {
+                completed: self.http_completed,
+                errors: self.http_errors,
+                interesting: self.http_interesting,
+                status_codes: self.status_codes.clone(),
+                tcp_states: self.tcp_states.clone(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AsnCount {
+    pub asn: String,
+    pub hosts: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HttpSummary {
+    pub completed: usize,
+    pub errors: usize,
+    /// Responses matching `--interesting-status`
+    pub interesting: usize,
+    pub status_codes: BTreeMap<u16, usize>,
+    /// Port states from `--tcp-connect`
+    pub tcp_states: BTreeMap<&'static str, usize>,
+}
+
+/// End-of-run report, printed for humans or written out as JSON with
+/// `--summary-json`.
+#[derive(Debug, Serialize)]
+pub struct SummaryReport {
+    pub elapsed_secs: f64,
+    pub hostnames: usize,
+    pub invalid: usize,
+    pub resolved: usize,
+    pub failed: usize,
+    pub error_kinds: BTreeMap<&'static str, usize>,
+    pub unique_ips: usize,
+    pub top_asns: Vec<AsnCount>,
+    pub http: HttpSummary,
+}
+
+impl fmt::Display for SummaryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Processing completed in {:.2?}", Duration::from_secs_f64(self.elapsed_secs))?;
+        writeln!(f, "Total hostnames processed: {}", self.hostnames)?;
+        if self.invalid > 0 {
+            writeln!(f, "Invalid entries skipped: {}", self.invalid)?;
+        }
+        writeln!(f, "Successfully resolved: {}", self.resolved)?;
+        if self.failed > 0 {
+            let kinds = self
+                .error_kinds
+                .iter()
+                .map(|(kind, count)| format!("{} {}", count, kind))
+                .collect::<Vec<_>>();
+            writeln!(f, "Failed: {} ({})", self.failed, kinds.join(", "))?;
+        }
+        writeln!(f, "Unique IPs: {}", self.unique_ips)?;
+        if !self.top_asns.is_empty() {
+            writeln!(f, "Top ASNs:")?;
+            for entry in &self.top_asns {
+                writeln!(f, "    {:>6}  {}", entry.hosts, entry.asn)?;
+            }
+        }
+        write!(f, "HTTP requests completed: {}", self.http.completed)?;
+        if !self.http.status_codes.is_empty() || self.http.errors > 0 {
+            let mut codes = self
+                .http
+                .status_codes
+                .iter()
+                .map(|(code, count)| format!("{} x{}", code, count))
+                .collect::<Vec<_>>();
+            if self.http.errors > 0 {
+                codes.push(format!("errors x{}", self.http.errors));
+            }
+            write!(f, "\nHTTP statuses: {}", codes.join(", "))?;
+            write!(f, "\nInteresting responses: {}", self.http.interesting)?;
+        }
+        if !self.http.tcp_states.is_empty() {
+            let states = self
+                .http
+                .tcp_states
+                .iter()
+                .map(|(state, count)| format!("{} {}", count, state))
+                .collect::<Vec<_>>();
+            write!(f, "\nTCP ports: {}", states.join(", "))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use rand::{distributions::Alphanumeric, Rng};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+use tokio::{sync::OnceCell, time::timeout};
+use trust_dns_resolver::TokioAsyncResolver;
+
+use crate::{lookup_records, DnsResult, ResolveOptions};
+
+/// Number of random names resolved under each parent domain.
+const PROBES: usize = 3;
+
+type ParentRecords = HashMap<String, Arc<OnceCell<HashSet<String>>>>;
+
+/// Detects wildcard DNS by resolving random, almost certainly unregistered
+/// names under each parent domain. A host whose records are all among the
+/// ones those random names got is indistinguishable from the wildcard. Each
+/// parent is probed once per run, the first time a host under it resolves.
+#[derive(Clone, Default)]
+pub struct WildcardDetector {
+    parents: Arc<Mutex<ParentRecords>>,
+}
+
+impl WildcardDetector {
+    pub async fn is_wildcard(
+        &self,
+        resolver: &TokioAsyncResolver,
+        result: &DnsResult,
+        options: &ResolveOptions,
+    ) -> bool {
+        if result.records.is_empty() {
+            return false;
+        }
+        let Some(parent) = probe_parent(&result.hostname) else {
+            return false;
+        };
+
+        let cell = self
+            .parents
+            .lock()
+            .expect("wildcard cache lock poisoned")
+            .entry(parent.to_string())
+            .or_default()
+            .clone();
+        let wildcard = cell.get_or_init(|| probe(resolver, parent, options)).await;
+
+        matches_wildcard(&result.records, wildcard)
+    }
+}
+
+/// Parent domain to probe for a wildcard covering `hostname`; none directly
+/// under a TLD, which is never probed.
+fn probe_parent(hostname: &str) -> Option<&str> {
+    let (_, parent) = hostname.split_once('.')?;
+    parent.contains('.').then_some(parent)
+}
+
+/// Whether a host's records are all among the `wildcard` records its parent
+/// returned for random names. Nothing matches when there is no wildcard.
+fn matches_wildcard(records: &[String], wildcard: &HashSet<String>) -> bool {
+    !records.is_empty() && !wildcard.is_empty() && records.iter().all(|record| wildcard.contains(record))
+}
+
+/// Records returned for random names under `parent`; empty when it has no
+/// wildcard.
+async fn probe(resolver: &TokioAsyncResolver, parent: &str, options: &ResolveOptions) -> HashSet<String> {
+    let mut records = HashSet::new();
+    for _ in 0..PROBES {
+        let label = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(16)
+            .map(|c| char::from(c).to_ascii_lowercase())
+            .collect::<String>();
+        let name = format!("{}.{}", label, parent);
+        options.throttle().await;
+        let lookup = lookup_records(resolver, &name, options.record_type, options.ip_version);
+        if let Ok(Ok(found)) = timeout(options.timeout, lookup).await {
+            records.extend(found.records);
+        }
+    }
+    if !records.is_empty() {
+        tracing::debug!("Wildcard DNS under {}: {:?}", parent, records);
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn records_within_wildcard_set_match() {
+        let wildcard: HashSet<String> = strings(&["192.0.2.1", "192.0.2.2"]).into_iter().collect();
+        assert!(matches_wildcard(&strings(&["192.0.2.1"]), &wildcard));
+        assert!(matches_wildcard(&strings(&["192.0.2.2", "192.0.2.1"]), &wildcard));
+        assert!(!matches_wildcard(&strings(&["192.0.2.1", "198.51.100.7"]), &wildcard));
+        assert!(!matches_wildcard(&strings(&["198.51.100.7"]), &wildcard));
+        assert!(!matches_wildcard(&[], &wildcard));
+    }
+
+    #[test]
+    fn nothing_matches_without_wildcard() {
+        assert!(!matches_wildcard(&strings(&["192.0.2.1"]), &HashSet::new()));
+    }
+
+    #[test]
+    fn parents_directly_under_tld_are_not_probed() {
+        assert_eq!(probe_parent("www.example.com"), Some("example.com"));
+        assert_eq!(probe_parent("a.b.example.co.uk"), Some("b.example.co.uk"));
+        assert_eq!(probe_parent("example.com"), None);
+        assert_eq!(probe_parent("localhost"), None);
+    }
+}